@@ -0,0 +1,293 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher as StdHasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use file::FileContent;
+use grouping::Deduplicator;
+
+/// Archive formats whose members can be expanded into virtual `FileContent` entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    TarXz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Guesses the archive format from a path's name, if recognized.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.to_string_lossy();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar.xz") {
+            Some(ArchiveFormat::TarXz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// One member inside an archive, addressable independently of its siblings.
+/// Members are read-only: they're reported as duplicates for inspection, but
+/// unlike on-disk `FileSet`s they're never hardlinked together.
+#[derive(Debug, Clone)]
+pub struct ArchiveMember {
+    pub archive_path: PathBuf,
+    pub member_name: String,
+}
+
+/// Removes its scratch file when dropped, so a scan never leaks disk space
+/// beyond the lifetime of whatever is still reading from the extracted file.
+///
+/// Kept separate from `ArchiveEntry` itself (rather than implementing `Drop`
+/// on it directly) so `into_content` can move the plain, ownable `FileContent`
+/// out without running into Rust's ban on partial moves out of `Drop` types.
+#[derive(Debug)]
+pub struct ScratchGuard(PathBuf);
+
+impl Drop for ScratchGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// A scratch file extracted from an archive member, plus the `FileContent`
+/// reading from it.
+#[derive(Debug)]
+pub struct ArchiveEntry {
+    pub member: ArchiveMember,
+    pub content: FileContent,
+    guard: ScratchGuard,
+}
+
+impl ArchiveEntry {
+    /// Splits this entry into an owned `FileContent` and the guard keeping
+    /// its scratch file alive. Hold onto the guard for as long as `content`
+    /// is used elsewhere (e.g. fed into a `Deduplicator`) — the scratch file
+    /// is removed as soon as the guard is dropped.
+    pub fn into_content(self) -> (FileContent, ScratchGuard) {
+        (self.content, self.guard)
+    }
+}
+
+/// Expands `archive_path` into one `ArchiveEntry` per regular-file member, so
+/// the existing size/prefix/hash comparison logic runs unchanged against
+/// content that lives inside an archive.
+///
+/// Each member is streamed through `libarchive` into a throwaway file under
+/// `scratch_dir`, since `FileContent` and `Hasher` are built around real
+/// filesystem paths; this keeps the comparison path identical to on-disk
+/// scanning at the cost of writing the member out once. The scratch file is
+/// cleaned up when its entry's `ScratchGuard` is dropped, so callers should
+/// hold onto that guard for as long as they need the content.
+pub fn scan_archive(archive_path: &Path, scratch_dir: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    if ArchiveFormat::from_path(archive_path).is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: not a recognized archive format (.tar, .tar.gz, .tar.xz, .zip)", archive_path.display()),
+        ));
+    }
+
+    let mut entries = Vec::new();
+
+    let mut reader = libarchive::Reader::open_file(archive_path)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    while let Some(mut member) = reader
+        .next_header()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+    {
+        if !member.is_file() {
+            continue;
+        }
+
+        let member_name = member.pathname().to_string();
+        let scratch_path = scratch_dir.join(scratch_name(archive_path, &member_name));
+
+        let mut out = fs::File::create(&scratch_path)?;
+        if let Err(e) = io::copy(&mut member, &mut out) {
+            let _ = fs::remove_file(&scratch_path);
+            return Err(e);
+        }
+
+        let content = match FileContent::from_path(&scratch_path) {
+            Ok(content) => content,
+            Err(e) => {
+                let _ = fs::remove_file(&scratch_path);
+                return Err(e);
+            }
+        };
+
+        entries.push(ArchiveEntry {
+            member: ArchiveMember {
+                archive_path: archive_path.to_path_buf(),
+                member_name: member_name,
+            },
+            content: content,
+            guard: ScratchGuard(scratch_path),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// A duplicate group that may mix on-disk files with archive members.
+///
+/// `archive_paths` calls out which of `files` were extracted from an archive
+/// rather than read directly off disk — per `ArchiveMember`'s doc comment,
+/// those are never safe to hand to `FileSet::dedup` as a hardlink/reflink
+/// source or destination, since their scratch files disappear once scanning
+/// is done.
+#[derive(Debug)]
+pub struct MixedGroup {
+    pub files: Vec<FileContent>,
+    pub archive_paths: Vec<PathBuf>,
+}
+
+/// Scans `archive_path` and groups its members together with `on_disk_files`
+/// through the same `Deduplicator` funnel used for a plain directory scan, so
+/// a duplicate payload is found whether it lives inside the archive or next
+/// to it. Returns the groups alongside the scratch guards backing any archive
+/// members — keep the guards alive for as long as the groups are inspected,
+/// since dropping one removes its scratch file.
+pub fn group_with_archive(
+    archive_path: &Path,
+    scratch_dir: &Path,
+    on_disk_files: Vec<FileContent>,
+    dedup: &Deduplicator,
+) -> io::Result<(Vec<MixedGroup>, Vec<ScratchGuard>)> {
+    let entries = scan_archive(archive_path, scratch_dir)?;
+
+    let mut files = on_disk_files;
+    let mut guards = Vec::with_capacity(entries.len());
+    let mut archive_backed: HashSet<PathBuf> = HashSet::with_capacity(entries.len());
+    for entry in entries {
+        let (content, guard) = entry.into_content();
+        archive_backed.insert(content.path().to_path_buf());
+        files.push(content);
+        guards.push(guard);
+    }
+
+    let groups = dedup
+        .group(files)
+        .into_iter()
+        .map(|files| {
+            let archive_paths = files
+                .iter()
+                .filter(|f| archive_backed.contains(f.path()))
+                .map(|f| f.path().to_path_buf())
+                .collect();
+            MixedGroup { files: files, archive_paths: archive_paths }
+        })
+        .collect();
+
+    Ok((groups, guards))
+}
+
+/// Deterministic scratch filename for a member, so repeated scans of the
+/// same archive reuse the same file instead of growing `scratch_dir` unbounded.
+fn scratch_name(archive_path: &Path, member_name: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    archive_path.hash(&mut hasher);
+    member_name.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[test]
+fn recognizes_supported_archive_extensions() {
+    assert_eq!(ArchiveFormat::from_path(Path::new("backup.tar")), Some(ArchiveFormat::Tar));
+    assert_eq!(ArchiveFormat::from_path(Path::new("backup.tar.gz")), Some(ArchiveFormat::TarGz));
+    assert_eq!(ArchiveFormat::from_path(Path::new("backup.tgz")), Some(ArchiveFormat::TarGz));
+    assert_eq!(ArchiveFormat::from_path(Path::new("backup.tar.xz")), Some(ArchiveFormat::TarXz));
+    assert_eq!(ArchiveFormat::from_path(Path::new("release.zip")), Some(ArchiveFormat::Zip));
+    assert_eq!(ArchiveFormat::from_path(Path::new("notes.txt")), None);
+}
+
+#[test]
+fn scan_archive_rejects_unrecognized_extension() {
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("archivetest").unwrap();
+    let err = scan_archive(Path::new("notes.txt"), dir.path()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn scratch_name_is_deterministic_per_member() {
+    let archive = Path::new("backup.tar");
+    assert_eq!(scratch_name(archive, "a/b.txt"), scratch_name(archive, "a/b.txt"));
+    assert_ne!(scratch_name(archive, "a/b.txt"), scratch_name(archive, "a/c.txt"));
+}
+
+#[test]
+fn scan_archive_extracts_member_matching_on_disk_content() {
+    use std::process::Command;
+    use tempdir::TempDir;
+
+    let src_dir = TempDir::new("archivetest-src").unwrap();
+    let scratch_dir = TempDir::new("archivetest-scratch").unwrap();
+
+    let on_disk_path = src_dir.path().join("hello.txt");
+    fs::write(&on_disk_path, b"hello from inside the archive").unwrap();
+
+    let archive_path = src_dir.path().join("bundle.tar");
+    let status = Command::new("tar")
+        .arg("-cf").arg(&archive_path)
+        .arg("-C").arg(src_dir.path())
+        .arg("hello.txt")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let mut entries = scan_archive(&archive_path, scratch_dir.path()).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].member.member_name, "hello.txt");
+
+    let on_disk = FileContent::from_path(&on_disk_path).unwrap();
+    let (extracted, _guard) = entries.remove(0).into_content();
+    assert_eq!(extracted, on_disk);
+}
+
+#[test]
+fn group_with_archive_finds_duplicate_shared_with_on_disk_file() {
+    use std::process::Command;
+    use tempdir::TempDir;
+
+    let src_dir = TempDir::new("archivetest-src").unwrap();
+    let scratch_dir = TempDir::new("archivetest-scratch").unwrap();
+
+    let member_path = src_dir.path().join("payload.txt");
+    fs::write(&member_path, b"duplicate payload").unwrap();
+
+    let archive_path = src_dir.path().join("bundle.tar");
+    let status = Command::new("tar")
+        .arg("-cf").arg(&archive_path)
+        .arg("-C").arg(src_dir.path())
+        .arg("payload.txt")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let on_disk_path = src_dir.path().join("copy.txt");
+    fs::write(&on_disk_path, b"duplicate payload").unwrap();
+    let on_disk_files = vec![FileContent::from_path(&on_disk_path).unwrap()];
+
+    let (groups, _guards) = group_with_archive(
+        &archive_path,
+        scratch_dir.path(),
+        on_disk_files,
+        &Deduplicator::new(),
+    ).unwrap();
+
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].files.len(), 2);
+    assert_eq!(groups[0].archive_paths.len(), 1);
+}
@@ -0,0 +1,235 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use file::FileSet;
+use handle::Handle;
+
+/// How the duplicates in a `FileSet` should be merged into one physical file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupStrategy {
+    /// Replace duplicates with hardlinks to the first path in the set.
+    Hardlink,
+    /// Replace duplicates with copy-on-write clones of the first path, so
+    /// each file keeps independent metadata while sharing physical blocks.
+    /// Falls back to `Hardlink`, then to `Copy`, on filesystems that don't
+    /// support reflinks.
+    Reflink,
+    /// Replace duplicates with independent copies of the first path.
+    Copy,
+}
+
+impl FileSet {
+    /// Merges every path after the first into the first path according to `strategy`.
+    pub fn dedup(&self, strategy: DedupStrategy) -> io::Result<()> {
+        let (original, duplicates) = self.paths.split_first().expect("FileSet is never empty");
+        for duplicate in duplicates {
+            dedup_one(original, duplicate, strategy)?;
+        }
+        Ok(())
+    }
+}
+
+fn dedup_one(original: &Path, duplicate: &Path, strategy: DedupStrategy) -> io::Result<()> {
+    // Already the same file on disk (e.g. hardlinked together, or bind-mounted)?
+    // Then there's nothing to link or copy.
+    if let (Ok(h1), Ok(h2)) = (Handle::from_path(original), Handle::from_path(duplicate)) {
+        if h1 == h2 {
+            return Ok(());
+        }
+    }
+
+    match strategy {
+        DedupStrategy::Hardlink => replace_with_hardlink(original, duplicate),
+        DedupStrategy::Reflink => match reflink(original, duplicate) {
+            Ok(()) => Ok(()),
+            Err(ref e) if is_reflink_unsupported(e) => replace_with_hardlink(original, duplicate)
+                .or_else(|_| replace_with_copy(original, duplicate)),
+            Err(e) => Err(e),
+        },
+        DedupStrategy::Copy => replace_with_copy(original, duplicate),
+    }
+}
+
+/// Whether `err` indicates the filesystem just doesn't support reflinks
+/// (as opposed to a real failure, like a permissions error or a full disk),
+/// which is the only case `DedupStrategy::Reflink` should fall back from.
+fn is_reflink_unsupported(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(code) => code == libc::EOPNOTSUPP || code == libc::ENOTTY || code == libc::EXDEV,
+        None => false,
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn replace_with_hardlink(original: &Path, duplicate: &Path) -> io::Result<()> {
+    remove_if_exists(duplicate)?;
+    fs::hard_link(original, duplicate)
+}
+
+fn replace_with_copy(original: &Path, duplicate: &Path) -> io::Result<()> {
+    remove_if_exists(duplicate)?;
+    fs::copy(original, duplicate).map(|_| ())
+}
+
+fn reflink(original: &Path, duplicate: &Path) -> io::Result<()> {
+    imp::reflink(original, duplicate)
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    // From <linux/fs.h>; a plain ioctl(2) clone request copy-on-write clones
+    // the whole destination from the source on filesystems that support it
+    // (e.g. Btrfs, XFS with reflink=1).
+    const FICLONE: libc::c_ulong = 0x40049409;
+
+    pub fn reflink(original: &Path, duplicate: &Path) -> io::Result<()> {
+        super::remove_if_exists(duplicate)?;
+        let src = File::open(original)?;
+        let dst = OpenOptions::new().write(true).create_new(true).open(duplicate)?;
+        let ret = unsafe { libc::ioctl(dst.as_raw_fd(), FICLONE, src.as_raw_fd()) };
+        if ret != 0 {
+            let err = io::Error::last_os_error();
+            let _ = super::remove_if_exists(duplicate);
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    extern "C" {
+        fn clonefile(src: *const libc::c_char, dst: *const libc::c_char, flags: u32) -> libc::c_int;
+    }
+
+    pub fn reflink(original: &Path, duplicate: &Path) -> io::Result<()> {
+        // clonefile() requires the destination path not to exist yet.
+        super::remove_if_exists(duplicate)?;
+        let src = CString::new(original.as_os_str().as_bytes())?;
+        let dst = CString::new(duplicate.as_os_str().as_bytes())?;
+        let ret = unsafe { clonefile(src.as_ptr(), dst.as_ptr(), 0) };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod imp {
+    use std::io;
+    use std::path::Path;
+
+    pub fn reflink(_original: &Path, _duplicate: &Path) -> io::Result<()> {
+        // Use the same "unsupported" errno the other platforms' reflink
+        // calls fail with, so `is_reflink_unsupported` falls back here too.
+        Err(io::Error::from_raw_os_error(libc::ENOTTY))
+    }
+}
+
+#[cfg(test)]
+fn write_file(path: &Path, content: &[u8]) {
+    use std::io::Write;
+    let mut f = fs::File::create(path).unwrap();
+    f.write_all(content).unwrap();
+}
+
+#[test]
+fn hardlink_strategy_links_duplicate_to_original() {
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("deduptest").unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    write_file(&a, b"hello");
+    write_file(&b, b"hello");
+
+    let mut set = FileSet::new(a.clone());
+    set.push(b.clone());
+    set.dedup(DedupStrategy::Hardlink).unwrap();
+
+    assert_eq!(Handle::from_path(&a).unwrap(), Handle::from_path(&b).unwrap());
+}
+
+#[test]
+fn copy_strategy_overwrites_without_linking() {
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("deduptest").unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    write_file(&a, b"hello");
+    write_file(&b, b"goodbye");
+
+    let mut set = FileSet::new(a.clone());
+    set.push(b.clone());
+    set.dedup(DedupStrategy::Copy).unwrap();
+
+    assert_eq!(fs::read(&b).unwrap(), b"hello");
+    assert_ne!(Handle::from_path(&a).unwrap(), Handle::from_path(&b).unwrap());
+}
+
+#[test]
+fn reflink_strategy_falls_back_and_converges_content() {
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("deduptest").unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    write_file(&a, b"hello");
+    write_file(&b, b"goodbye");
+
+    let mut set = FileSet::new(a.clone());
+    set.push(b.clone());
+    // On filesystems/platforms without reflink support this falls back to a
+    // hardlink or a copy, but must still succeed and converge content.
+    set.dedup(DedupStrategy::Reflink).unwrap();
+
+    assert_eq!(fs::read(&a).unwrap(), fs::read(&b).unwrap());
+}
+
+#[test]
+fn already_linked_files_are_left_alone() {
+    use std::os::unix::fs::PermissionsExt;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("deduptest").unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    write_file(&a, b"hello");
+    fs::hard_link(&a, &b).unwrap();
+
+    // Make the directory read-only so a spurious remove_file + relink would
+    // fail; dedup should recognize that `a` and `b` already share an inode
+    // and skip linking entirely.
+    let mut perms = fs::metadata(dir.path()).unwrap().permissions();
+    perms.set_mode(0o555);
+    fs::set_permissions(dir.path(), perms.clone()).unwrap();
+
+    let mut set = FileSet::new(a.clone());
+    set.push(b.clone());
+    let result = set.dedup(DedupStrategy::Reflink);
+
+    perms.set_mode(0o755);
+    fs::set_permissions(dir.path(), perms).unwrap();
+
+    result.unwrap();
+}
@@ -1,9 +1,11 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::cmp::Ordering;
 use std::sync::Mutex;
 use std::io;
 use metadata::Metadata;
 use hasher::Hasher;
+use handle::Handle;
+use phash::PerceptualHash;
 
 #[derive(Debug, Clone)]
 pub struct FileSet {
@@ -30,6 +32,12 @@ pub struct FileContent {
     metadata: Metadata,
     /// Hashes of content, calculated incrementally
     hashes: Mutex<Hasher>,
+    /// OS-level file identity, resolved lazily and cached on first use.
+    /// `Some(None)` once resolution has been tried and failed.
+    handle: Mutex<Option<Option<Handle>>>,
+    /// Perceptual hash, for recognized image types; cached on first use.
+    /// `Some(None)` once computed for a file that isn't a decodable image.
+    image_hash: Mutex<Option<Option<PerceptualHash>>>,
 }
 
 impl FileContent {
@@ -45,8 +53,55 @@ impl FileContent {
             path: path,
             metadata: metadata,
             hashes: Mutex::new(Hasher::new()),
+            handle: Mutex::new(None),
+            image_hash: Mutex::new(None),
         }
     }
+
+    /// The path this content is read from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns this file's OS-level identity, opening the file to resolve it
+    /// the first time it's needed. `None` if the file can no longer be opened.
+    fn handle(&self) -> Option<Handle> {
+        let mut slot = self.handle.lock().unwrap();
+        if slot.is_none() {
+            *slot = Some(Handle::from_path(&self.path).ok());
+        }
+        slot.clone().unwrap()
+    }
+
+    /// This file's `(size, device)`, used as the first and cheapest grouping
+    /// key by `Deduplicator`. Device is included because files on different
+    /// devices can never be duplicates as far as this crate is concerned
+    /// (see `Metadata::cmp`), and bucketing on size alone would otherwise
+    /// pay for prefix hashing and full comparison across devices for nothing.
+    pub fn size_key(&self) -> (u64, u64) {
+        let device = self.handle().map(|h| h.device()).unwrap_or(0);
+        (self.metadata.size, device)
+    }
+
+    /// Hash of this file's leading `len` bytes, used by `Deduplicator` as a
+    /// cheap pre-filter before falling back to full comparison. Goes through
+    /// the same incremental `Hasher` used by `partial_cmp`, so the bytes read
+    /// here are cached and don't need rehashing when a later full comparison
+    /// picks up where this left off.
+    pub fn prefix_hash(&self, len: usize) -> Result<u64, io::Error> {
+        let mut hashes = self.hashes.lock().unwrap();
+        hashes.hash_upto(len as u64, &self.path)
+    }
+
+    /// This file's perceptual hash, if it's a decodable image; `None` otherwise.
+    /// See `phash::group_similar` for grouping files by visual similarity.
+    pub fn perceptual_hash(&self) -> Option<PerceptualHash> {
+        let mut slot = self.image_hash.lock().unwrap();
+        if slot.is_none() {
+            *slot = Some(PerceptualHash::from_path(&self.path).ok());
+        }
+        slot.clone().unwrap()
+    }
 }
 
 impl Eq for FileContent {
@@ -80,6 +135,15 @@ impl PartialOrd for FileContent {
             return Some(Ordering::Equal);
         }
 
+        // Same-file check: if both paths already resolve to the same inode
+        // (Unix) or file index (Windows), they're already hardlinked together
+        // and there's no point reading and hashing their contents.
+        if let (Some(h1), Some(h2)) = (self.handle(), other.handle()) {
+            if h1 == h2 {
+                return Some(Ordering::Equal);
+            }
+        }
+
         let mut hashes1 = self.hashes.lock().unwrap();
         let mut hashes2 = other.hashes.lock().unwrap();
 
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+use file::FileContent;
+
+/// Groups candidate files into sets of byte-for-byte duplicates, without
+/// paying for full-file hashing up front.
+///
+/// Runs a three-stage funnel, each stage more expensive than the last, and
+/// drops any bucket that's down to a single file as early as possible since
+/// a singleton can't be a duplicate of anything:
+///
+/// 1. bucket by `(size, device)` — files with a unique size, or on a
+///    different device, can't be duplicates as far as this crate is concerned
+/// 2. within each surviving bucket, bucket again by the hash of a small
+///    prefix of the content (this is usually enough to rule out the rest)
+/// 3. within each surviving prefix bucket, fall back to the existing full
+///    incremental comparison (`FileContent`'s `Ord` impl) to confirm matches
+///
+/// Most files never get read past their first few KiB. Stages 2 and 3
+/// dispatch independent buckets to a rayon thread pool, so hashing unrelated
+/// files proceeds in parallel.
+pub struct Deduplicator {
+    /// Number of leading bytes hashed in the prefix stage.
+    prefix_len: usize,
+    /// Number of worker threads to hash with, or `None` to use rayon's default.
+    threads: Option<usize>,
+}
+
+impl Deduplicator {
+    pub fn new() -> Self {
+        Deduplicator {
+            prefix_len: 4096,
+            threads: None,
+        }
+    }
+
+    /// Sets the number of leading bytes hashed in the prefix stage (default 4 KiB).
+    pub fn with_prefix_len(mut self, prefix_len: usize) -> Self {
+        self.prefix_len = prefix_len;
+        self
+    }
+
+    /// Sets the number of threads used to hash and compare candidate buckets
+    /// (default: rayon's own choice, usually one per core).
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Groups `files` into sets of files that are byte-for-byte identical.
+    /// Files that turn out unique at any stage are simply dropped from the result.
+    pub fn group(&self, files: Vec<FileContent>) -> Vec<Vec<FileContent>> {
+        match self.threads {
+            Some(threads) => {
+                let pool = ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .expect("failed to build dedup thread pool");
+                pool.install(|| self.group_on_current_pool(files))
+            }
+            None => self.group_on_current_pool(files),
+        }
+    }
+
+    fn group_on_current_pool(&self, files: Vec<FileContent>) -> Vec<Vec<FileContent>> {
+        drop_singletons(bucket_by(files, |f| f.size_key()))
+            .into_par_iter()
+            .flat_map(|(_, by_size)| {
+                drop_singletons(bucket_by(by_size, |f| f.prefix_hash(self.prefix_len).ok()))
+                    .into_par_iter()
+                    .flat_map(|(_, by_prefix)| group_by_full_content(by_prefix))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Final stage: split a prefix bucket into groups of files that are truly
+/// identical, using `FileContent`'s own incremental comparison (which
+/// re-checks metadata and handles, and reuses its own cached hashes).
+fn group_by_full_content(bucket: Vec<FileContent>) -> Vec<Vec<FileContent>> {
+    let mut groups: Vec<Vec<FileContent>> = Vec::new();
+
+    'files: for file in bucket {
+        for group in &mut groups {
+            if group[0] == file {
+                group.push(file);
+                continue 'files;
+            }
+        }
+        groups.push(vec![file]);
+    }
+
+    groups.into_iter().filter(|g| g.len() > 1).collect()
+}
+
+fn bucket_by<T, K, F>(items: Vec<T>, key: F) -> HashMap<K, Vec<T>>
+where
+    K: Eq + Hash,
+    F: Fn(&T) -> K,
+{
+    let mut buckets: HashMap<K, Vec<T>> = HashMap::new();
+    for item in items {
+        let k = key(&item);
+        buckets.entry(k).or_insert_with(Vec::new).push(item);
+    }
+    buckets
+}
+
+fn drop_singletons<K, T>(buckets: HashMap<K, Vec<T>>) -> HashMap<K, Vec<T>>
+where
+    K: Eq + Hash,
+{
+    buckets.into_iter().filter(|&(_, ref v)| v.len() > 1).collect()
+}
+
+#[cfg(test)]
+fn write(dir: &::tempdir::TempDir, name: &str, content: &[u8]) -> ::std::path::PathBuf {
+    use std::fs::File;
+    use std::io::Write;
+
+    let path = dir.path().join(name);
+    let mut f = File::create(&path).unwrap();
+    f.write_all(content).unwrap();
+    path
+}
+
+#[test]
+fn groups_identical_files_and_drops_uniques() {
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("groupingtest").unwrap();
+    let a = write(&dir, "a", b"AAAAAAAAAA");
+    let b = write(&dir, "b", b"AAAAAAAAAA");
+    let c = write(&dir, "c", b"AAAAAAAAAB"); // same size, different content
+    let d = write(&dir, "d", b"SHORT"); // unique size
+
+    let files = vec![
+        FileContent::from_path(&a).unwrap(),
+        FileContent::from_path(&b).unwrap(),
+        FileContent::from_path(&c).unwrap(),
+        FileContent::from_path(&d).unwrap(),
+    ];
+
+    let groups = Deduplicator::new().group(files);
+
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].len(), 2);
+}
+
+#[test]
+fn full_comparison_catches_divergence_after_matching_prefix() {
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("groupingtest").unwrap();
+    let a = write(&dir, "a", b"AAAAtail-one");
+    let b = write(&dir, "b", b"AAAAtail-two");
+
+    let files = vec![
+        FileContent::from_path(&a).unwrap(),
+        FileContent::from_path(&b).unwrap(),
+    ];
+
+    // A 4-byte prefix matches for both files, so stage 2 can't tell them
+    // apart; stage 3's full comparison must still catch the difference.
+    let groups = Deduplicator::new().with_prefix_len(4).group(files);
+
+    assert!(groups.is_empty());
+}
+
+#[test]
+fn dispatches_across_a_configured_thread_pool() {
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("groupingtest").unwrap();
+    let a = write(&dir, "a", b"AAAAAAAAAA");
+    let b = write(&dir, "b", b"AAAAAAAAAA");
+    let c = write(&dir, "c", b"SHORT");
+
+    let files = vec![
+        FileContent::from_path(&a).unwrap(),
+        FileContent::from_path(&b).unwrap(),
+        FileContent::from_path(&c).unwrap(),
+    ];
+
+    let groups = Deduplicator::new().with_threads(2).group(files);
+
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].len(), 2);
+}
@@ -0,0 +1,116 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Cheap, OS-level identity of an open file.
+///
+/// Two `Handle`s compare equal when they refer to the same underlying file,
+/// regardless of which path was used to open it (e.g. two hardlinks, or a
+/// symlink and its target).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handle(imp::Handle);
+
+impl Handle {
+    /// Opens `path` and captures the identity of the file it resolves to.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let file = File::open(path.as_ref())?;
+        Ok(Handle(imp::Handle::from_file(&file)?))
+    }
+
+    /// Identifier of the device (Unix) or volume (Windows) this file lives on.
+    pub fn device(&self) -> u64 {
+        self.0.device()
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::fs::MetadataExt;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Handle {
+        dev: u64,
+        ino: u64,
+    }
+
+    impl Handle {
+        pub fn from_file(file: &File) -> Result<Self, io::Error> {
+            let m = file.metadata()?;
+            Ok(Handle {
+                dev: m.dev(),
+                ino: m.ino(),
+            })
+        }
+
+        pub fn device(&self) -> u64 {
+            self.dev
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::fs::File;
+    use std::io;
+    use std::mem;
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::fileapi::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+    use winapi::um::winnt::HANDLE;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Handle {
+        volume_serial_number: u32,
+        file_index: u64,
+    }
+
+    impl Handle {
+        pub fn from_file(file: &File) -> Result<Self, io::Error> {
+            unsafe {
+                let mut info: BY_HANDLE_FILE_INFORMATION = mem::zeroed();
+                let ok = GetFileInformationByHandle(file.as_raw_handle() as HANDLE, &mut info);
+                if ok == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let file_index = ((info.nFileIndexHigh as u64) << 32) | (info.nFileIndexLow as u64);
+                Ok(Handle {
+                    volume_serial_number: info.dwVolumeSerialNumber,
+                    file_index: file_index,
+                })
+            }
+        }
+
+        pub fn device(&self) -> u64 {
+            self.volume_serial_number as u64
+        }
+    }
+}
+
+#[test]
+fn hardlinked_files_have_same_handle() {
+    use std::fs;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("handletest").unwrap();
+    let a_path = dir.path().join("a");
+    let b_path = dir.path().join("b");
+
+    let mut a_fd = fs::File::create(&a_path).unwrap();
+    a_fd.write_all(b"hello").unwrap();
+    drop(a_fd);
+
+    fs::hard_link(&a_path, &b_path).unwrap();
+
+    let a = Handle::from_path(&a_path).unwrap();
+    let b = Handle::from_path(&b_path).unwrap();
+    assert_eq!(a, b);
+}
+
+#[test]
+fn distinct_files_have_different_handles() {
+    let a = Handle::from_path("tests/a").unwrap();
+    let b = Handle::from_path("tests/b").unwrap();
+    assert_ne!(a, b);
+}
@@ -0,0 +1,15 @@
+extern crate libc;
+extern crate rayon;
+extern crate image;
+extern crate libarchive;
+#[cfg(test)]
+extern crate tempdir;
+
+pub mod handle;
+pub mod metadata;
+pub mod hasher;
+pub mod file;
+pub mod dedup;
+pub mod grouping;
+pub mod archive;
+pub mod phash;
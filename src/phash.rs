@@ -0,0 +1,122 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use image::{self, imageops::FilterType, GenericImageView};
+
+/// 64-bit perceptual fingerprint of an image's rough visual content.
+///
+/// Computed as a difference hash (dHash): downscale to a small grayscale
+/// grid, then set each bit according to whether a pixel is brighter than its
+/// right neighbor. Unlike `Hasher`'s cryptographic hash, two images with
+/// close fingerprints may differ byte-for-byte and still look alike, so this
+/// is never used for hardlink-safe exact-match decisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerceptualHash(u64);
+
+impl PerceptualHash {
+    /// The grid is `(GRID_SIZE + 1) x GRID_SIZE`, since each row needs one
+    /// extra column of samples to compare against the last real column.
+    const GRID_SIZE: u32 = 8;
+
+    /// Decodes the image at `path` and computes its dHash. Fails if the path
+    /// isn't a recognized, decodable image format.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let img = image::open(path.as_ref())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            .resize_exact(Self::GRID_SIZE + 1, Self::GRID_SIZE, FilterType::Triangle)
+            .to_luma8();
+
+        let mut bits = 0u64;
+        let mut bit = 0;
+        for y in 0..Self::GRID_SIZE {
+            for x in 0..Self::GRID_SIZE {
+                let left = img.get_pixel(x, y)[0];
+                let right = img.get_pixel(x + 1, y)[0];
+                if left > right {
+                    bits |= 1 << bit;
+                }
+                bit += 1;
+            }
+        }
+
+        Ok(PerceptualHash(bits))
+    }
+
+    /// Number of differing bits between two fingerprints; 0 means visually identical.
+    pub fn hamming_distance(&self, other: &Self) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+/// A group of images judged visually similar by perceptual hash.
+///
+/// Unlike `FileSet`, this is not hardlink-safe: members can differ
+/// byte-for-byte (different encoding, resolution, or metadata) while still
+/// looking alike, so callers should only ever report these, never link them.
+#[derive(Debug, Clone)]
+pub struct SimilarImageSet {
+    pub paths: Vec<PathBuf>,
+}
+
+/// Groups image paths whose perceptual hashes are within `max_distance`
+/// Hamming distance (out of 64 bits) of the first image in a group; each
+/// image joins the first such group, in input order. Members are not checked
+/// against one another, so two members of one group can be up to
+/// `2 * max_distance` apart. Lower thresholds require closer visual matches;
+/// singletons are dropped from the result.
+pub fn group_similar(images: Vec<(PathBuf, PerceptualHash)>, max_distance: u32) -> Vec<SimilarImageSet> {
+    let mut groups: Vec<SimilarImageSet> = Vec::new();
+    let mut group_hashes: Vec<PerceptualHash> = Vec::new();
+
+    for (path, hash) in images {
+        let existing = group_hashes
+            .iter()
+            .position(|h| h.hamming_distance(&hash) <= max_distance);
+
+        match existing {
+            Some(i) => groups[i].paths.push(path),
+            None => {
+                groups.push(SimilarImageSet { paths: vec![path] });
+                group_hashes.push(hash);
+            }
+        }
+    }
+
+    groups.into_iter().filter(|g| g.paths.len() > 1).collect()
+}
+
+#[test]
+fn hamming_distance_counts_differing_bits() {
+    let a = PerceptualHash(0b1010);
+    let b = PerceptualHash(0b1000);
+    assert_eq!(a.hamming_distance(&b), 1);
+    assert_eq!(a.hamming_distance(&a), 0);
+}
+
+#[test]
+fn group_similar_clusters_within_threshold_and_drops_singletons() {
+    let images = vec![
+        (PathBuf::from("a"), PerceptualHash(0b0000_0000)),
+        (PathBuf::from("b"), PerceptualHash(0b0000_0001)), // 1 bit from a
+        (PathBuf::from("c"), PerceptualHash(0b1111_1111)), // far from everything
+    ];
+
+    let groups = group_similar(images, 1);
+
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].paths, vec![PathBuf::from("a"), PathBuf::from("b")]);
+}
+
+#[test]
+fn group_similar_raising_threshold_merges_more_images() {
+    let images = vec![
+        (PathBuf::from("a"), PerceptualHash(0b0000_0000)),
+        (PathBuf::from("b"), PerceptualHash(0b0000_0011)), // 2 bits from a
+    ];
+
+    assert!(group_similar(images.clone(), 1).is_empty());
+
+    let groups = group_similar(images, 2);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].paths.len(), 2);
+}